@@ -0,0 +1,124 @@
+//! Decompression of a block's on-disk payload, keyed off the dnode's
+//! `compress` property, so hex-dumps can show logical file data instead of
+//! the compressed bytes actually sitting on disk.
+
+use crate::Error;
+
+/// Compression algorithm recorded on the dnode, as parsed from zdb's
+/// `compress` key/value line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Off,
+    Lz4,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Parse the `compress` dnode property, e.g. `lz4` or `off (inherited
+    /// from dataset)`. Unrecognized values fall back to `Off`.
+    pub fn from_property(value: &str) -> Self {
+        let value = value.to_ascii_lowercase();
+        if value.contains("lz4") {
+            Compression::Lz4
+        } else if value.contains("gzip") {
+            Compression::Gzip
+        } else if value.contains("zstd") {
+            Compression::Zstd
+        } else {
+            Compression::Off
+        }
+    }
+}
+
+/// Decompress `raw` (the on-disk, physical-size payload) into an
+/// `lsize`-byte logical buffer, per the dnode's compression algorithm.
+/// `Off` returns a copy of `raw` unchanged, whatever its length.
+pub fn decompress_block(raw: &[u8], lsize: u64, algo: Compression) -> Result<Vec<u8>, Error> {
+    match algo {
+        Compression::Off => Ok(raw.to_vec()),
+        Compression::Lz4 => decompress_lz4(raw, lsize),
+        Compression::Gzip => decompress_gzip(raw, lsize),
+        Compression::Zstd => decompress_zstd(raw, lsize),
+    }
+}
+
+/// ZFS's LZ4 framing: a 4-byte big-endian length prefix giving the size of
+/// the compressed payload, followed by a standard LZ4 block of that many
+/// bytes, which expands to exactly `lsize` bytes.
+fn decompress_lz4(raw: &[u8], lsize: u64) -> Result<Vec<u8>, Error> {
+    if raw.len() < 4 {
+        return Err(Error::Decompress(
+            "lz4 payload shorter than the 4-byte length prefix".to_string(),
+        ));
+    }
+    let compressed_len = u32::from_be_bytes(raw[0..4].try_into().unwrap()) as usize;
+    let compressed = raw.get(4..4 + compressed_len).ok_or_else(|| {
+        Error::Decompress(format!(
+            "lz4 length prefix {compressed_len} exceeds payload"
+        ))
+    })?;
+
+    lz4_flex::block::decompress(compressed, lsize as usize)
+        .map_err(|e| Error::Decompress(format!("lz4: {e}")))
+}
+
+#[cfg(feature = "gzip")]
+fn decompress_gzip(raw: &[u8], lsize: u64) -> Result<Vec<u8>, Error> {
+    use std::io::Read;
+    let mut decoder = flate2::read::GzDecoder::new(raw);
+    let mut out = Vec::with_capacity(lsize as usize);
+    decoder.read_to_end(&mut out).map_err(Error::Io)?;
+    Ok(out)
+}
+
+#[cfg(not(feature = "gzip"))]
+fn decompress_gzip(_raw: &[u8], _lsize: u64) -> Result<Vec<u8>, Error> {
+    Err(Error::Decompress(
+        "gzip decompression requires building with the \"gzip\" feature".to_string(),
+    ))
+}
+
+#[cfg(feature = "zstd")]
+fn decompress_zstd(raw: &[u8], lsize: u64) -> Result<Vec<u8>, Error> {
+    zstd::bulk::decompress(raw, lsize as usize).map_err(Error::Io)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn decompress_zstd(_raw: &[u8], _lsize: u64) -> Result<Vec<u8>, Error> {
+    Err(Error::Decompress(
+        "zstd decompression requires building with the \"zstd\" feature".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decompresses_a_length_prefixed_lz4_block() {
+        let logical = b"the quick brown fox jumps over the lazy dog";
+        let compressed = lz4_flex::block::compress(logical);
+        let mut raw = (compressed.len() as u32).to_be_bytes().to_vec();
+        raw.extend_from_slice(&compressed);
+
+        let out = decompress_lz4(&raw, logical.len() as u64).expect("should decompress");
+        assert_eq!(out, logical);
+    }
+
+    #[test]
+    fn rejects_a_payload_shorter_than_the_length_prefix() {
+        let err = decompress_lz4(&[0, 0, 1], 4).expect_err("3 bytes can't hold a 4-byte prefix");
+        assert!(matches!(err, Error::Decompress(_)));
+    }
+
+    #[test]
+    fn rejects_a_length_prefix_that_exceeds_the_payload() {
+        // Prefix claims 100 compressed bytes, but only 2 follow it.
+        let mut raw = 100u32.to_be_bytes().to_vec();
+        raw.extend_from_slice(&[0xaa, 0xbb]);
+
+        let err = decompress_lz4(&raw, 4).expect_err("prefix exceeds payload");
+        assert!(matches!(err, Error::Decompress(_)));
+    }
+}
@@ -0,0 +1,26 @@
+//! The error type shared by the zdb discovery and parsing code, so callers
+//! can tell "not a ZFS filesystem" apart from "zdb isn't installed" apart
+//! from "this block line doesn't match the grammar".
+
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("not a ZFS filesystem (found {found})")]
+    NotZfs { found: String },
+
+    #[error("failed to spawn zdb")]
+    ZdbSpawn(#[source] std::io::Error),
+
+    #[error("could not determine inode: {0}")]
+    BadInode(String),
+
+    #[error("line {line_no}: {text}")]
+    BadBlockLine { line_no: usize, text: String },
+
+    #[error("decompression failed: {0}")]
+    Decompress(String),
+
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
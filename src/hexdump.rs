@@ -0,0 +1,154 @@
+//! Reading the raw bytes a `BlockInfo`'s DVA points at, and rendering them
+//! as an annotated hex+ASCII view for eyeballing on-disk corruption.
+
+use std::ops::Range;
+use std::process::Command;
+
+use crate::parser::DVAInfo;
+use crate::Error;
+
+const BYTES_PER_ROW: usize = 16;
+
+/// Read the physical bytes a DVA points at via `zdb -R pool vdev:offset:size:r`,
+/// which dumps the raw on-disk block to stdout.
+pub fn read_block_bytes(pool: &str, dva: &DVAInfo) -> Result<Vec<u8>, Error> {
+    let spec = format!("{}:{:x}:{:x}:r", dva.vdev, dva.offset, dva.size);
+    let output = Command::new("zdb")
+        .arg("-R")
+        .arg(pool)
+        .arg(&spec)
+        .output()
+        .map_err(Error::ZdbSpawn)?;
+
+    if !output.status.success() {
+        return Err(Error::Io(std::io::Error::other(
+            String::from_utf8_lossy(&output.stderr).into_owned(),
+        )));
+    }
+    Ok(output.stdout)
+}
+
+/// Renders a byte slice as hex+ASCII: 16 bytes per row, a hex offset
+/// gutter, a printable-ASCII column, and an optional highlighted range
+/// (e.g. a block's header).
+pub struct HexView<'a> {
+    data: &'a [u8],
+    base_offset: u64,
+    highlight: Option<Range<usize>>,
+}
+
+impl<'a> HexView<'a> {
+    pub fn builder(data: &'a [u8]) -> HexViewBuilder<'a> {
+        HexViewBuilder {
+            data,
+            base_offset: 0,
+            highlight: None,
+        }
+    }
+
+    /// Render as a multi-line string, one row per 16 bytes.
+    pub fn render(&self) -> String {
+        use std::fmt::Write;
+        let mut out = String::new();
+        for (row_idx, row) in self.data.chunks(BYTES_PER_ROW).enumerate() {
+            let row_offset = self.base_offset + (row_idx * BYTES_PER_ROW) as u64;
+            let _ = write!(out, "{row_offset:08x}  ");
+
+            for col in 0..BYTES_PER_ROW {
+                match row.get(col) {
+                    Some(byte) if self.is_highlighted(row_idx * BYTES_PER_ROW + col) => {
+                        let _ = write!(out, "[{byte:02x}]");
+                    }
+                    Some(byte) => {
+                        let _ = write!(out, " {byte:02x} ");
+                    }
+                    None => out.push_str("    "),
+                }
+            }
+
+            out.push_str(" |");
+            for byte in row {
+                let c = *byte as char;
+                out.push(if c.is_ascii_graphic() || c == ' ' {
+                    c
+                } else {
+                    '.'
+                });
+            }
+            out.push('|');
+            out.push('\n');
+        }
+        out
+    }
+
+    fn is_highlighted(&self, index: usize) -> bool {
+        self.highlight.as_ref().is_some_and(|r| r.contains(&index))
+    }
+}
+
+pub struct HexViewBuilder<'a> {
+    data: &'a [u8],
+    base_offset: u64,
+    highlight: Option<Range<usize>>,
+}
+
+impl<'a> HexViewBuilder<'a> {
+    /// Offset printed in the gutter for the first row (typically the
+    /// block's DVA physical offset).
+    pub fn base_offset(mut self, offset: u64) -> Self {
+        self.base_offset = offset;
+        self
+    }
+
+    /// Byte range (relative to `data`) to wrap in brackets, e.g. the
+    /// block's header.
+    pub fn highlight(mut self, range: Range<usize>) -> Self {
+        self.highlight = Some(range);
+        self
+    }
+
+    pub fn build(self) -> HexView<'a> {
+        HexView {
+            data: self.data,
+            base_offset: self.base_offset,
+            highlight: self.highlight,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_a_partial_row_with_padding_and_ascii() {
+        let data = b"Hi!";
+        let view = HexView::builder(data).build();
+        assert_eq!(
+            view.render(),
+            "00000000   48  69  21                                                      |Hi!|\n"
+        );
+    }
+
+    #[test]
+    fn applies_base_offset_to_the_gutter() {
+        let data = [0u8; 1];
+        let view = HexView::builder(&data).base_offset(0x1000).build();
+        assert!(view.render().starts_with("00001000  "));
+    }
+
+    #[test]
+    fn brackets_bytes_inside_the_highlight_range() {
+        let data = [0xaa, 0xbb, 0xcc];
+        let view = HexView::builder(&data).highlight(1..2).build();
+        let rendered = view.render();
+        assert!(rendered.contains(" aa [bb] cc "));
+    }
+
+    #[test]
+    fn wraps_to_a_new_row_every_16_bytes() {
+        let data = [0u8; 20];
+        let view = HexView::builder(&data).build();
+        assert_eq!(view.render().lines().count(), 2);
+    }
+}
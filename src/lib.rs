@@ -0,0 +1,137 @@
+//! Library half of `zfileinfo`: turning the output of `zdb -ddddd` into a
+//! dnode key/value map and a stream of indirect-block pointers. The binary
+//! (`main.rs`) is just the CLI shell around this: finding the dataset and
+//! inode for a path, spawning `zdb`, and rendering the results.
+
+pub mod checksum;
+pub mod compress;
+pub mod error;
+pub mod hexdump;
+pub mod parser;
+
+pub use error::Error;
+pub use parser::{BlockInfo, DVAInfo};
+
+use std::collections::HashMap;
+use std::io::BufRead;
+
+/// Which section of `zdb -ddddd` output the line-oriented scan is in while
+/// collecting the dnode key/value map.
+#[derive(Debug, PartialEq)]
+enum HeaderState {
+    Dataset,       // Initial state, expecting dataset information line
+    ObjectHeader,  // Object information header state (after first blank line)
+    KeyValuePairs, // State for parsing key-value pairs (starting from dnode flags)
+}
+
+/// The dnode key/value map plus a lazy stream of its block pointers.
+pub struct ZdbAnalysis<R> {
+    pub kv_map: HashMap<String, String>,
+    pub blocks: BlockIter<R>,
+}
+
+/// Lazily yields one `BlockInfo` (or `Error::BadBlockLine`) per indirect
+/// block line, so a multi-gigabyte dump with millions of blocks doesn't
+/// have to be buffered into a `Vec` before the caller sees any output.
+pub struct BlockIter<R> {
+    reader: R,
+    line_no: usize,
+    done: bool,
+}
+
+impl<R: BufRead> Iterator for BlockIter<R> {
+    type Item = Result<BlockInfo, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        let mut line = String::new();
+        let bytes_read = match self.reader.read_line(&mut line) {
+            Ok(n) => n,
+            Err(e) => {
+                self.done = true;
+                return Some(Err(Error::Io(e)));
+            }
+        };
+        if bytes_read == 0 {
+            self.done = true;
+            return None;
+        }
+        self.line_no += 1;
+        let text = line.trim_end_matches('\n');
+        if text.is_empty() {
+            self.done = true;
+            return None;
+        }
+
+        Some(
+            parser::parse_block_line(text).map_err(|e| Error::BadBlockLine {
+                line_no: self.line_no,
+                text: format!("{text} ({e})"),
+            }),
+        )
+    }
+}
+
+/// Parse the output of `zdb -ddddd <dataset> <inode>` into its dnode
+/// key/value map and a lazy iterator over its indirect block pointers.
+///
+/// The key/value map is collected eagerly (it's small and precedes the
+/// block list), while `ZdbAnalysis::blocks` reads and parses block lines
+/// one at a time as the caller iterates.
+pub fn parse_zdb_reader<R: BufRead>(mut reader: R) -> Result<ZdbAnalysis<R>, Error> {
+    let mut kv_map = HashMap::new();
+    let mut state = HeaderState::Dataset;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        let bytes_read = reader.read_line(&mut line)?;
+        if bytes_read == 0 {
+            break;
+        }
+        let text = line.trim_end_matches('\n');
+        let trimmed = text.trim();
+
+        match state {
+            HeaderState::Dataset => {
+                if trimmed.is_empty() {
+                    state = HeaderState::ObjectHeader;
+                }
+            }
+            HeaderState::ObjectHeader => {
+                if trimmed.starts_with("dnode flags:") {
+                    state = HeaderState::KeyValuePairs;
+                }
+            }
+            HeaderState::KeyValuePairs => {
+                if text.starts_with(' ') || text.starts_with('\t') {
+                    if let Some((key, value)) = trimmed.split_once(':') {
+                        kv_map.insert(key.trim().to_string(), value.trim().to_string());
+                    } else {
+                        let mut parts = trimmed.splitn(2, char::is_whitespace);
+                        if let (Some(key), Some(value)) = (parts.next(), parts.next()) {
+                            if !key.is_empty() && !value.trim().is_empty() {
+                                kv_map.insert(key.trim().to_string(), value.trim().to_string());
+                            }
+                        }
+                    }
+                }
+                if text.starts_with("Indirect blocks") {
+                    break;
+                }
+            }
+        }
+    }
+
+    Ok(ZdbAnalysis {
+        kv_map,
+        blocks: BlockIter {
+            reader,
+            line_no: 0,
+            done: false,
+        },
+    })
+}
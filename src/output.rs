@@ -0,0 +1,217 @@
+//! Rendering of the collected dnode key/value map and block list in the
+//! format requested on the command line. Each format consumes the block
+//! list as an iterator rather than a slice, so formats that can emit a
+//! result per block (`Json`, `Csv`) don't need the whole list resident in
+//! memory first.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use serde::{Serialize, Serializer};
+
+use zfileinfo::BlockInfo;
+
+/// Output format selected with `--format`/`-f`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable Debug-formatted listing (the historical default).
+    Text,
+    /// One JSON object per invocation, with numeric block fields.
+    Json,
+    /// Header row plus one row per block, for piping into other tools.
+    Csv,
+}
+
+/// A `BlockInfo` flattened for machine-readable output: the DVA fields are
+/// inlined and the checksum is split into its four hex words.
+#[derive(Serialize)]
+struct BlockRecord {
+    offset: u64,
+    level: u32,
+    vdev: u32,
+    dva_offset: u64,
+    dva_size: u64,
+    lsize: Option<u64>,
+    psize: Option<u64>,
+    fill_count: Option<u32>,
+    birth_time: Option<String>,
+    checksum_words: Option<[u64; 4]>,
+}
+
+impl From<&BlockInfo> for BlockRecord {
+    fn from(b: &BlockInfo) -> Self {
+        BlockRecord {
+            offset: b.offset,
+            level: b.level,
+            vdev: b.dva.vdev,
+            dva_offset: b.dva.offset,
+            dva_size: b.dva.size,
+            lsize: b.lsize,
+            psize: b.psize,
+            fill_count: b.fill_count,
+            birth_time: b.birth_time.clone(),
+            checksum_words: b.checksum_words(),
+        }
+    }
+}
+
+/// `BlockRecord`, but with `checksum_words` split into four scalar columns.
+/// `csv::Writer::serialize` flattens an array field into that many cells,
+/// which would silently grow each populated row past the column count
+/// `CSV_RECORD_FIELDS` reserves for it in the header; giving the checksum
+/// its own fixed columns keeps every row the same width as the header.
+#[derive(Serialize)]
+struct CsvBlockRecord {
+    offset: u64,
+    level: u32,
+    vdev: u32,
+    dva_offset: u64,
+    dva_size: u64,
+    lsize: Option<u64>,
+    psize: Option<u64>,
+    fill_count: Option<u32>,
+    birth_time: Option<String>,
+    checksum_word0: Option<u64>,
+    checksum_word1: Option<u64>,
+    checksum_word2: Option<u64>,
+    checksum_word3: Option<u64>,
+}
+
+impl From<&BlockInfo> for CsvBlockRecord {
+    fn from(b: &BlockInfo) -> Self {
+        let words = b.checksum_words();
+        CsvBlockRecord {
+            offset: b.offset,
+            level: b.level,
+            vdev: b.dva.vdev,
+            dva_offset: b.dva.offset,
+            dva_size: b.dva.size,
+            lsize: b.lsize,
+            psize: b.psize,
+            fill_count: b.fill_count,
+            birth_time: b.birth_time.clone(),
+            checksum_word0: words.map(|w| w[0]),
+            checksum_word1: words.map(|w| w[1]),
+            checksum_word2: words.map(|w| w[2]),
+            checksum_word3: words.map(|w| w[3]),
+        }
+    }
+}
+
+/// Serializes as a JSON array, pulling `BlockRecord`s from an iterator one
+/// at a time instead of collecting them into a `Vec` first.
+struct BlockRecordSeq<I>(RefCell<I>);
+
+impl<I> Serialize for BlockRecordSeq<I>
+where
+    I: Iterator<Item = BlockRecord>,
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.borrow_mut().by_ref())
+    }
+}
+
+struct Report<'a, I: Iterator<Item = BlockRecord>> {
+    dnode: &'a HashMap<String, String>,
+    blocks: BlockRecordSeq<I>,
+}
+
+impl<I: Iterator<Item = BlockRecord>> Serialize for Report<'_, I> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut report = serializer.serialize_struct("Report", 2)?;
+        report.serialize_field("dnode", self.dnode)?;
+        report.serialize_field("blocks", &self.blocks)?;
+        report.end()
+    }
+}
+
+/// Print the collected dnode key/value pairs and block list to stdout in
+/// `format`. Text output buffers at most 10 blocks to decide whether to
+/// print them or just a count (10 or more blocks print only the count);
+/// `Json` and `Csv` stream every block as it's pulled from `blocks`.
+pub fn print_results(
+    format: OutputFormat,
+    kv_map: &HashMap<String, String>,
+    blocks: impl Iterator<Item = BlockInfo>,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Text => print_text(kv_map, blocks),
+        OutputFormat::Json => print_json(kv_map, blocks),
+        OutputFormat::Csv => print_csv(blocks),
+    }
+}
+
+fn print_text(kv_map: &HashMap<String, String>, blocks: impl Iterator<Item = BlockInfo>) -> io::Result<()> {
+    println!("Collected key-value pairs before 'Indirect blocks':");
+    for (key, value) in kv_map {
+        println!("{}: {}", key, value);
+    }
+
+    const PREVIEW_LIMIT: usize = 10;
+    let mut preview = Vec::with_capacity(PREVIEW_LIMIT);
+    let mut total = 0usize;
+    for block in blocks {
+        total += 1;
+        if preview.len() < PREVIEW_LIMIT {
+            preview.push(block);
+        }
+    }
+
+    if total < PREVIEW_LIMIT {
+        println!("\nCollected block info after 'Indirect blocks':");
+        for block in &preview {
+            println!("{:?}", block);
+        }
+    } else {
+        println!("\nCollected {total} block info entries after 'Indirect blocks'. Use --format json or csv for details.");
+    }
+    Ok(())
+}
+
+fn print_json(kv_map: &HashMap<String, String>, blocks: impl Iterator<Item = BlockInfo>) -> io::Result<()> {
+    let report = Report {
+        dnode: kv_map,
+        blocks: BlockRecordSeq(RefCell::new(blocks.map(|b| BlockRecord::from(&b)))),
+    };
+    serde_json::to_writer_pretty(io::stdout(), &report).map_err(io::Error::other)?;
+    println!();
+    Ok(())
+}
+
+/// Field names for `CsvBlockRecord`, in declaration order, so the header
+/// row can be written without needing a record (or the full block list) in
+/// hand — `csv::Writer` otherwise only emits a header on the first
+/// `serialize` call, so zero blocks meant zero output at all.
+const CSV_RECORD_FIELDS: &[&str] = &[
+    "offset",
+    "level",
+    "vdev",
+    "dva_offset",
+    "dva_size",
+    "lsize",
+    "psize",
+    "fill_count",
+    "birth_time",
+    "checksum_word0",
+    "checksum_word1",
+    "checksum_word2",
+    "checksum_word3",
+];
+
+fn print_csv(blocks: impl Iterator<Item = BlockInfo>) -> io::Result<()> {
+    let mut writer = csv::WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(io::stdout());
+    writer
+        .write_record(CSV_RECORD_FIELDS)
+        .map_err(io::Error::other)?;
+    for block in blocks {
+        writer
+            .serialize(CsvBlockRecord::from(&block))
+            .map_err(io::Error::other)?;
+    }
+    writer.flush()?;
+    io::stdout().flush()
+}
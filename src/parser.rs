@@ -0,0 +1,221 @@
+//! Grammar for zdb block-pointer lines, built on `winnow` combinators.
+//!
+//! A block line looks like:
+//!   0 L2   1:167eb4000:2000 20000L/c00P F=44771 B=78/78 cksum=14a59e34a60:1ad4d903aff01:191e38ff2cb6471:28b1e6c089abd03d
+//!
+//! i.e. a hex logical offset, an `L<level>` token, a `vdev:offset:size` DVA
+//! triple, an `<lsize>L/<psize>P` size pair, and zero or more `KEY=value`
+//! fields. Unlike the old `split_whitespace`/`unwrap_or(0)` approach, a line
+//! that doesn't match this shape produces a `ParseError` with the byte
+//! offset of the failure instead of a `BlockInfo` full of zeros.
+
+use winnow::ascii::{dec_uint, hex_digit1, space1};
+use winnow::combinator::{alt, eof, preceded, repeat};
+use winnow::prelude::*;
+use winnow::token::take_while;
+
+/// nb, offset is technically 63 bits, with the top bit used for a GRID or GANG block indicator.
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+pub struct DVAInfo {
+    pub vdev: u32,   // vdev id (first part of DVA, e.g. 1)
+    pub offset: u64, // offset (second part of DVA, e.g. 167eb4000)
+    pub size: u64,   // size of the block (third part of DVA, e.g. 2000) (XXX what's the unit here?)
+}
+
+impl DVAInfo {
+    /// A DVA of `0:0:0` is how zdb denotes a hole (a block of unallocated,
+    /// implicitly-zero logical data) rather than a real on-disk location.
+    pub fn is_hole(&self) -> bool {
+        self.vdev == 0 && self.offset == 0 && self.size == 0
+    }
+}
+
+/// Represents a block info/pointer line from zdb output, e.g.:
+///    0 L2   1:167eb4000:2000 20000L/c00P F=44771 B=78/78 cksum=14a59e34a60:1ad4d903aff01:191e38ff2cb6471:28b1e6c089abd03d
+#[derive(Debug, PartialEq, Clone, serde::Serialize)]
+pub struct BlockInfo {
+    pub offset: u64,  // Logical offset (hex without prefix, e.g. 0 or 20000 not 0x...)
+    pub level: u32,   // Block level (e.g. L2 -> 2)
+    pub dva: DVAInfo, // DVA information
+    pub lsize: Option<u64>, // Logical size (number before 'L' in PSIZE/LSIZE, e.g. 20000)
+    pub psize: Option<u64>, // Physical size (number before 'P' in PSIZE/LSIZE, e.g. c00 or aa00 or 20000)
+    pub fill_count: Option<u32>, // F= value (fill count, e.g. 44771)
+    pub birth_time: Option<String>, // B= value (birth time, e.g. 78/78)
+    pub checksum: Option<String>, // cksum value (e.g. 14a59e34a60:...)
+}
+
+/// A block-pointer line failed to parse; `offset` is the byte position
+/// within the line where parsing gave up.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "parse error at byte {}: {}", self.offset, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+enum TaggedField {
+    Fill(u32),
+    Birth(String),
+    Cksum(String),
+}
+
+/// A run of hex digits parsed as a `u64`, e.g. `167eb4000`.
+fn hex_u64(input: &mut &str) -> ModalResult<u64> {
+    hex_digit1
+        .try_map(|s| u64::from_str_radix(s, 16))
+        .parse_next(input)
+}
+
+/// A `vdev:offset:size` triple, e.g. `1:167eb4000:2000`.
+fn dva(input: &mut &str) -> ModalResult<DVAInfo> {
+    let vdev = dec_uint.parse_next(input)?;
+    ':'.parse_next(input)?;
+    let offset = hex_u64.parse_next(input)?;
+    ':'.parse_next(input)?;
+    let size = hex_u64.parse_next(input)?;
+    Ok(DVAInfo { vdev, offset, size })
+}
+
+/// An `<lsize>L/<psize>P` pair, e.g. `20000L/c00P`.
+fn size_pair(input: &mut &str) -> ModalResult<(u64, u64)> {
+    let lsize = hex_u64.parse_next(input)?;
+    'L'.parse_next(input)?;
+    '/'.parse_next(input)?;
+    let psize = hex_u64.parse_next(input)?;
+    'P'.parse_next(input)?;
+    Ok((lsize, psize))
+}
+
+/// One `F=`, `B=`, or `cksum=` field.
+fn tagged_field(input: &mut &str) -> ModalResult<TaggedField> {
+    let not_space = |c: char| !c.is_whitespace();
+    alt((
+        preceded("F=", dec_uint).map(TaggedField::Fill),
+        preceded("B=", take_while(1.., not_space)).map(|s: &str| TaggedField::Birth(s.to_string())),
+        preceded("cksum=", take_while(1.., not_space))
+            .map(|s: &str| TaggedField::Cksum(s.to_string())),
+    ))
+    .parse_next(input)
+}
+
+/// The `L<level>` token, e.g. `L2`.
+fn level(input: &mut &str) -> ModalResult<u32> {
+    preceded('L', dec_uint).parse_next(input)
+}
+
+fn block_line(input: &mut &str) -> ModalResult<BlockInfo> {
+    let offset = hex_u64.parse_next(input)?;
+    space1.parse_next(input)?;
+    let level = level.parse_next(input)?;
+    space1.parse_next(input)?;
+    let dva = dva.parse_next(input)?;
+    space1.parse_next(input)?;
+    let (lsize, psize) = size_pair.parse_next(input)?;
+    let fields: Vec<TaggedField> = repeat(0.., preceded(space1, tagged_field)).parse_next(input)?;
+    // Any content left over (an unrecognized tagged field, stray trailing
+    // text, ...) is a parse error rather than silently-dropped garbage.
+    eof.parse_next(input)?;
+
+    let mut fill_count = None;
+    let mut birth_time = None;
+    let mut checksum = None;
+    for field in fields {
+        match field {
+            TaggedField::Fill(f) => fill_count = Some(f),
+            TaggedField::Birth(b) => birth_time = Some(b),
+            TaggedField::Cksum(c) => checksum = Some(c),
+        }
+    }
+
+    Ok(BlockInfo {
+        offset,
+        level,
+        dva,
+        lsize: Some(lsize),
+        psize: Some(psize),
+        fill_count,
+        birth_time,
+        checksum,
+    })
+}
+
+impl BlockInfo {
+    /// Split `checksum` into its four 64-bit hex words, e.g.
+    /// `14a59e34a60:1ad4d903aff01:191e38ff2cb6471:28b1e6c089abd03d`, for
+    /// formats that want numeric fields rather than the raw string.
+    pub fn checksum_words(&self) -> Option<[u64; 4]> {
+        let cksum = self.checksum.as_deref()?;
+        let parts: Vec<&str> = cksum.split(':').collect();
+        if parts.len() != 4 {
+            return None;
+        }
+        let mut words = [0u64; 4];
+        for (word, part) in words.iter_mut().zip(parts) {
+            *word = u64::from_str_radix(part, 16).ok()?;
+        }
+        Some(words)
+    }
+}
+
+/// Parse a single block-pointer line from `zdb -ddddd` output.
+///
+/// Returns the byte offset of the failure within `line` on error, so callers
+/// can tell a parse failure from a line that legitimately contains zeros.
+pub fn parse_block_line(line: &str) -> Result<BlockInfo, ParseError> {
+    let original_len = line.len();
+    let mut input = line;
+    block_line.parse_next(&mut input).map_err(|e| ParseError {
+        offset: original_len - input.len(),
+        message: e.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID_LINE: &str = "0 L2   1:167eb4000:2000 20000L/c00P F=44771 B=78/78 cksum=14a59e34a60:1ad4d903aff01:191e38ff2cb6471:28b1e6c089abd03d";
+
+    #[test]
+    fn parses_a_valid_block_line() {
+        let block = parse_block_line(VALID_LINE).expect("should parse");
+        assert_eq!(block.offset, 0);
+        assert_eq!(block.level, 2);
+        assert_eq!(
+            block.dva,
+            DVAInfo {
+                vdev: 1,
+                offset: 0x167eb4000,
+                size: 0x2000,
+            }
+        );
+        assert_eq!(block.lsize, Some(0x20000));
+        assert_eq!(block.psize, Some(0xc00));
+        assert_eq!(block.fill_count, Some(44771));
+        assert_eq!(block.birth_time.as_deref(), Some("78/78"));
+        assert_eq!(
+            block.checksum.as_deref(),
+            Some("14a59e34a60:1ad4d903aff01:191e38ff2cb6471:28b1e6c089abd03d")
+        );
+    }
+
+    #[test]
+    fn rejects_trailing_garbage_after_recognized_fields() {
+        let line = format!("{VALID_LINE} trailing garbage text");
+        let err = parse_block_line(&line).expect_err("trailing content should be rejected");
+        assert_eq!(err.offset, VALID_LINE.len());
+    }
+
+    #[test]
+    fn rejects_unrecognized_tagged_field() {
+        let line = "0 L2 1:167eb4000:2000 20000L/c00P UNKNOWN=garbage";
+        assert!(parse_block_line(line).is_err());
+    }
+}